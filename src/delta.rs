@@ -0,0 +1,384 @@
+//! Rsync-style delta encoding for compact storage of related C3D trials.
+//!
+//! Labs frequently record many near-identical trials that differ only in a
+//! handful of frames or parameter edits. Rather than storing every trial in
+//! full, a [`Signature`] of a base file can be built once and reused to
+//! encode later trials as a small patch of copied base ranges and literal
+//! bytes, following the classic rsync rolling-checksum algorithm.
+
+use crate::processor::Processor;
+use crate::{C3d, C3dParseError, C3dWriteError};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Default block size, in bytes, used when building a [`Signature`].
+pub const DEFAULT_BLOCK_SIZE: usize = 2048;
+
+/// A weak, O(1)-updatable rolling checksum over a sliding window of bytes,
+/// modeled on the Adler-32 algorithm used by rsync.
+#[derive(Debug, Clone, Copy, Default)]
+struct RollingHash {
+    a: u32,
+    b: u32,
+    len: u32,
+}
+
+impl RollingHash {
+    const MOD: u32 = 65521;
+
+    fn new(window: &[u8]) -> Self {
+        let mut hash = RollingHash::default();
+        for &byte in window {
+            hash.push(byte);
+        }
+        hash
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.a = (self.a + byte as u32) % Self::MOD;
+        self.b = (self.b + self.a) % Self::MOD;
+        self.len += 1;
+    }
+
+    /// Slides the window forward by one byte, removing `out` and adding `new`.
+    fn roll(&mut self, out: u8, new: u8) {
+        self.a = (self.a + Self::MOD - (out as u32 % Self::MOD)) % Self::MOD;
+        self.a = (self.a + new as u32) % Self::MOD;
+        self.b = (self.b + Self::MOD - ((self.len * out as u32) % Self::MOD)) % Self::MOD;
+        self.b = (self.b + self.a) % Self::MOD;
+    }
+
+    fn value(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+}
+
+/// A cheap strong hash used to confirm a weak-hash match is a genuine block
+/// match rather than a collision.
+fn strong_hash(block: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    block.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A signature of a base file: a weak rolling checksum and strong hash for
+/// every fixed-size block, used to find byte ranges a target file can copy
+/// from the base instead of storing them again.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    block_size: usize,
+    blocks: HashMap<u32, Vec<(u64, usize)>>,
+}
+
+impl Signature {
+    /// Builds a `Signature` of `base` by splitting it into `block_size`-byte
+    /// blocks (the final block may be shorter) and recording a weak/strong
+    /// hash pair for each, keyed by the weak hash so candidate matches can
+    /// be looked up in O(1) while diffing.
+    pub fn build(base: &[u8], block_size: usize) -> Self {
+        let mut blocks: HashMap<u32, Vec<(u64, usize)>> = HashMap::new();
+        for (index, block) in base.chunks(block_size).enumerate() {
+            let weak = RollingHash::new(block).value();
+            let strong = strong_hash(block);
+            blocks
+                .entry(weak)
+                .or_default()
+                .push((strong, index * block_size));
+        }
+        Signature { block_size, blocks }
+    }
+
+    /// Returns the base offset of a block matching `window`, verifying the
+    /// strong hash to rule out weak-hash collisions.
+    fn find(&self, weak: u32, window: &[u8]) -> Option<usize> {
+        let candidates = self.blocks.get(&weak)?;
+        let strong = strong_hash(window);
+        candidates
+            .iter()
+            .find(|(candidate_strong, _)| *candidate_strong == strong)
+            .map(|(_, offset)| *offset)
+    }
+}
+
+/// One piece of a diff between a base file and a target file: either a
+/// verbatim copy of a base byte range, or literal target bytes not covered
+/// by a matching base block.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    /// A byte range copied from the base file.
+    Copy { base_offset: usize, len: usize },
+    /// Target bytes not covered by a full-size window matching a base block.
+    /// These may still occur somewhere in the base (for example a trailing
+    /// run shorter than the block size).
+    Literal(Vec<u8>),
+}
+
+/// Diffs `target` against `signature`, producing the sequence of
+/// [`Segment`]s needed to reconstruct `target` from the base file the
+/// signature was built from.
+///
+/// A byte-by-byte rolling window is slid across `target`; whenever it
+/// matches a base block, that block is emitted as a `Copy` and the window
+/// advances a full block, otherwise the byte is accumulated into a
+/// `Literal` run.
+pub fn diff(signature: &Signature, target: &[u8]) -> Vec<Segment> {
+    let block_size = signature.block_size;
+    if block_size == 0 || target.is_empty() {
+        return if target.is_empty() {
+            Vec::new()
+        } else {
+            vec![Segment::Literal(target.to_vec())]
+        };
+    }
+
+    let mut segments = Vec::new();
+    let mut literal = Vec::new();
+    let mut start = 0;
+    let mut end = block_size.min(target.len());
+    let mut window = RollingHash::new(&target[start..end]);
+
+    while start < target.len() {
+        let matched = if end - start == block_size {
+            signature.find(window.value(), &target[start..end])
+        } else {
+            None
+        };
+
+        if let Some(base_offset) = matched {
+            if !literal.is_empty() {
+                segments.push(Segment::Literal(std::mem::take(&mut literal)));
+            }
+            segments.push(Segment::Copy {
+                base_offset,
+                len: end - start,
+            });
+            start = end;
+            end = (start + block_size).min(target.len());
+            if start < target.len() {
+                window = RollingHash::new(&target[start..end]);
+            }
+        } else {
+            literal.push(target[start]);
+            start += 1;
+            if end < target.len() {
+                window.roll(target[start - 1], target[end]);
+                end += 1;
+            } else if end > start {
+                window = RollingHash::new(&target[start..end]);
+            }
+        }
+    }
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    segments
+}
+
+/// Reconstructs a target file by applying `segments` to `base`.
+pub fn apply(base: &[u8], segments: &[Segment]) -> Result<Vec<u8>, C3dParseError> {
+    let mut out = Vec::new();
+    for segment in segments {
+        match segment {
+            Segment::Copy { base_offset, len } => {
+                let end = base_offset + len;
+                let range = base.get(*base_offset..end).ok_or_else(|| {
+                    C3dParseError::InvalidDelta(format!(
+                        "copy segment [{}, {}) is out of bounds for a {}-byte base file",
+                        base_offset,
+                        end,
+                        base.len()
+                    ))
+                })?;
+                out.extend_from_slice(range);
+            }
+            Segment::Literal(bytes) => out.extend_from_slice(bytes),
+        }
+    }
+    Ok(out)
+}
+
+/// The patch format's own encoding is a fixed little-endian layout,
+/// independent of the byte order of the C3D files it was diffed from: a
+/// patch is a self-contained artifact, not part of the C3D file format, so
+/// there is no reason to tie its structure to `Processor`. This also keeps
+/// `read_segments` free of any dependency on the caller passing the same
+/// `Processor` used by `write_segments`.
+///
+/// The header additionally records the base file's length, so a `base`
+/// passed to [`C3d::read_delta`] that serializes to a different length than
+/// the one the patch was built against is caught as an error instead of
+/// silently producing corrupted copy ranges. A different base of the same
+/// serialized length is not detected.
+fn write_segments(base_len: usize, segments: &[Segment]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend((base_len as u64).to_le_bytes());
+    for segment in segments {
+        match segment {
+            Segment::Copy { base_offset, len } => {
+                bytes.push(0);
+                bytes.extend((*base_offset as u64).to_le_bytes());
+                bytes.extend((*len as u64).to_le_bytes());
+            }
+            Segment::Literal(data) => {
+                bytes.push(1);
+                bytes.extend((data.len() as u64).to_le_bytes());
+                bytes.extend(data);
+            }
+        }
+    }
+    bytes
+}
+
+/// Deserializes a patch produced by [`write_segments`] back into its
+/// `Segment`s, checking that `base_len` (the actual base file's length)
+/// matches the length recorded when the patch was built.
+fn read_segments(base_len: usize, patch: &[u8]) -> Result<Vec<Segment>, C3dParseError> {
+    let read_u64 = |bytes: &[u8], at: usize| -> Result<u64, C3dParseError> {
+        let slice = bytes.get(at..at + 8).ok_or_else(|| {
+            C3dParseError::InvalidDelta("patch is truncated".to_string())
+        })?;
+        Ok(u64::from_le_bytes(slice.try_into().unwrap()))
+    };
+
+    let recorded_base_len = read_u64(patch, 0)? as usize;
+    if recorded_base_len != base_len {
+        return Err(C3dParseError::InvalidDelta(format!(
+            "patch was built against a {}-byte base file, but the supplied base is {} bytes",
+            recorded_base_len, base_len
+        )));
+    }
+
+    let mut segments = Vec::new();
+    let mut i = 8;
+    while i < patch.len() {
+        let tag = patch[i];
+        i += 1;
+        match tag {
+            0 => {
+                let base_offset = read_u64(patch, i)? as usize;
+                i += 8;
+                let len = read_u64(patch, i)? as usize;
+                i += 8;
+                segments.push(Segment::Copy { base_offset, len });
+            }
+            1 => {
+                let len = read_u64(patch, i)? as usize;
+                i += 8;
+                let data = patch
+                    .get(i..i + len)
+                    .ok_or_else(|| {
+                        C3dParseError::InvalidDelta(
+                            "literal segment length exceeds remaining patch bytes".to_string(),
+                        )
+                    })?
+                    .to_vec();
+                i += len;
+                segments.push(Segment::Literal(data));
+            }
+            other => {
+                return Err(C3dParseError::InvalidDelta(format!(
+                    "unrecognized delta segment tag {}",
+                    other
+                )))
+            }
+        }
+    }
+    Ok(segments)
+}
+
+impl C3d {
+    /// Encodes `self` as a compact patch against `base`. `processor`
+    /// controls the byte order `base` and `self` are serialized with (the
+    /// same as would be passed to their own [`C3d::write`]); the patch
+    /// structure itself has a fixed encoding. The patch records only the
+    /// base's serialized length, not the `processor`, so a different
+    /// `processor` at [`C3d::read_delta`] time that serializes `base` to the
+    /// same length is not detected. The patch can be turned back into `self`
+    /// with `read_delta` given the same `base` and `processor`.
+    pub fn write_delta(&self, base: &C3d, processor: &Processor) -> Result<Vec<u8>, C3dWriteError> {
+        let base_bytes = base.write(processor)?;
+        let target_bytes = self.write(processor)?;
+        let signature = Signature::build(&base_bytes, DEFAULT_BLOCK_SIZE);
+        let segments = diff(&signature, &target_bytes);
+        Ok(write_segments(base_bytes.len(), &segments))
+    }
+
+    /// Reconstructs a `C3d` previously encoded with [`C3d::write_delta`]
+    /// from `base` and the serialized `patch`.
+    pub fn read_delta(base: &C3d, patch: &[u8], processor: &Processor) -> Result<Self, C3dParseError> {
+        let base_bytes = base.write(processor).map_err(|err| {
+            C3dParseError::InvalidDelta(format!("failed to re-serialize base file: {}", err))
+        })?;
+        let segments = read_segments(base_bytes.len(), patch)?;
+        let target_bytes = apply(&base_bytes, &segments)?;
+        C3d::parse(&target_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg::{DataLimits, Seg};
+
+    #[test]
+    fn delta_round_trip_is_byte_exact_including_seg_section() {
+        let processor = Processor::Intel;
+
+        let mut base = C3d::default();
+        base.seg = Seg {
+            marker_diameter: Some(14.0),
+            data_limits: Some(
+                DataLimits::new(-500.0, 500.0, -500.0, 500.0, 0.0, 1500.0).unwrap(),
+            ),
+            acc_factor: Some(50.0),
+            noise_factor: Some(10.0),
+            residual_error_factor: Some(2.0),
+            intersection_limit: Some(0.7),
+        };
+
+        let mut target = base.clone();
+        target.seg.marker_diameter = Some(16.0);
+        target.seg.data_limits = Some(
+            DataLimits::new(-600.0, 600.0, -600.0, 600.0, 0.0, 1600.0).unwrap(),
+        );
+
+        let target_bytes = target.write(&processor).expect("target should write");
+
+        let patch = target
+            .write_delta(&base, &processor)
+            .expect("write_delta should succeed");
+        let reconstructed = C3d::read_delta(&base, &patch, &processor)
+            .expect("read_delta should succeed");
+        let reconstructed_bytes = reconstructed
+            .write(&processor)
+            .expect("reconstructed file should write");
+
+        assert_eq!(reconstructed_bytes, target_bytes);
+        assert_eq!(reconstructed.seg, target.seg);
+    }
+
+    #[test]
+    fn read_delta_rejects_mismatched_base() {
+        let processor = Processor::Intel;
+
+        let mut base = C3d::default();
+        base.seg.data_limits = Some(
+            DataLimits::new(-500.0, 500.0, -500.0, 500.0, 0.0, 1500.0).unwrap(),
+        );
+        let mut target = base.clone();
+        target.seg.marker_diameter = Some(16.0);
+
+        let patch = target
+            .write_delta(&base, &processor)
+            .expect("write_delta should succeed");
+
+        // A base that serializes to a different length than the one the
+        // patch was built against (dropping a whole parameter) must be
+        // rejected rather than silently producing garbage copy ranges.
+        let mut wrong_base = base.clone();
+        wrong_base.seg.data_limits = None;
+
+        assert!(C3d::read_delta(&wrong_base, &patch, &processor).is_err());
+    }
+}
@@ -0,0 +1,38 @@
+//! Error types returned when parsing or writing C3D files.
+use std::fmt;
+
+/// Errors that can occur while parsing a C3D file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum C3dParseError {
+    /// The SEG:DATA_LIMITS parameter did not describe valid per-axis bounds,
+    /// for example because it was an unexpected shape or had `min > max` on
+    /// some axis.
+    InvalidDataLimits(String),
+    /// A delta patch could not be decoded or applied to its base file.
+    InvalidDelta(String),
+}
+
+impl fmt::Display for C3dParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            C3dParseError::InvalidDataLimits(message) => {
+                write!(f, "invalid data limits: {}", message)
+            }
+            C3dParseError::InvalidDelta(message) => write!(f, "invalid delta patch: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for C3dParseError {}
+
+/// Errors that can occur while writing a C3D file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum C3dWriteError {}
+
+impl fmt::Display for C3dWriteError {
+    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {}
+    }
+}
+
+impl std::error::Error for C3dWriteError {}
@@ -5,6 +5,133 @@ use crate::{C3dParseError, C3dWriteError};
 use grid::Grid;
 use std::collections::HashMap;
 
+/// The per-axis minimum and maximum values recorded by the SEG:DATA_LIMITS
+/// parameter, describing the bounds the file's 3D point data was expected
+/// to fall within during reconstruction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DataLimits {
+    x_min: f32,
+    x_max: f32,
+    y_min: f32,
+    y_max: f32,
+    z_min: f32,
+    z_max: f32,
+}
+
+impl DataLimits {
+    /// Constructs a new `DataLimits`, returning a `C3dParseError` if any
+    /// axis has an inverted range (`min > max`).
+    pub fn new(
+        x_min: f32,
+        x_max: f32,
+        y_min: f32,
+        y_max: f32,
+        z_min: f32,
+        z_max: f32,
+    ) -> Result<Self, C3dParseError> {
+        if x_min > x_max || y_min > y_max || z_min > z_max {
+            return Err(C3dParseError::InvalidDataLimits(format!(
+                "data limits must have min <= max for every axis, found x: [{}, {}], y: [{}, {}], z: [{}, {}]",
+                x_min, x_max, y_min, y_max, z_min, z_max
+            )));
+        }
+        Ok(DataLimits {
+            x_min,
+            x_max,
+            y_min,
+            y_max,
+            z_min,
+            z_max,
+        })
+    }
+
+    /// The minimum recorded X value.
+    pub fn x_min(&self) -> f32 {
+        self.x_min
+    }
+
+    /// The maximum recorded X value.
+    pub fn x_max(&self) -> f32 {
+        self.x_max
+    }
+
+    /// The minimum recorded Y value.
+    pub fn y_min(&self) -> f32 {
+        self.y_min
+    }
+
+    /// The maximum recorded Y value.
+    pub fn y_max(&self) -> f32 {
+        self.y_max
+    }
+
+    /// The minimum recorded Z value.
+    pub fn z_min(&self) -> f32 {
+        self.z_min
+    }
+
+    /// The maximum recorded Z value.
+    pub fn z_max(&self) -> f32 {
+        self.z_max
+    }
+
+    /// Returns `true` if `point` falls within these bounds on every axis.
+    fn contains(&self, point: &[f32; 3]) -> bool {
+        point[0] >= self.x_min
+            && point[0] <= self.x_max
+            && point[1] >= self.y_min
+            && point[1] <= self.y_max
+            && point[2] >= self.z_min
+            && point[2] <= self.z_max
+    }
+
+    /// Builds a `DataLimits` from the raw `Grid<f32>` stored in the
+    /// SEG:DATA_LIMITS parameter. The parameter is usually laid out as a
+    /// 3x2 grid (one row per axis X/Y/Z, columns `[min, max]`), but C3D
+    /// parameter arrays are stored column-major on disk, so a file whose
+    /// parameter section round-tripped through a row/column-agnostic reader
+    /// may instead hand us the transposed 2x3 shape (one row per `[min,
+    /// max]`, columns X/Y/Z). Both are accepted.
+    pub(crate) fn from_grid(grid: &Grid<f32>) -> Result<Self, C3dParseError> {
+        if grid.rows() == 3 && grid.cols() == 2 {
+            DataLimits::new(
+                grid[(0, 0)],
+                grid[(0, 1)],
+                grid[(1, 0)],
+                grid[(1, 1)],
+                grid[(2, 0)],
+                grid[(2, 1)],
+            )
+        } else if grid.rows() == 2 && grid.cols() == 3 {
+            DataLimits::new(
+                grid[(0, 0)],
+                grid[(1, 0)],
+                grid[(0, 1)],
+                grid[(1, 1)],
+                grid[(0, 2)],
+                grid[(1, 2)],
+            )
+        } else {
+            Err(C3dParseError::InvalidDataLimits(format!(
+                "expected a 3x2 or 2x3 DATA_LIMITS grid, found {}x{}",
+                grid.rows(),
+                grid.cols()
+            )))
+        }
+    }
+
+    /// Converts back to the raw 3x2 `Grid<f32>` layout used by the
+    /// SEG:DATA_LIMITS parameter.
+    pub(crate) fn into_grid(self) -> Grid<f32> {
+        Grid::from_vec(
+            vec![
+                self.x_min, self.x_max, self.y_min, self.y_max, self.z_min, self.z_max,
+            ],
+            2,
+        )
+    }
+}
+
 /// Common in older C3D files, this parameter section is used to store
 /// parameters related to how the raw data was processed.
 /// Although this section is not required, it is recommended to include
@@ -15,10 +142,9 @@ pub struct Seg {
     /// The diameter of the marker in millimeters. It is good practice to
     /// use the same diameter for all markers in a collection.
     pub marker_diameter: Option<f32>,
-    /// A 3x2 grid of floats that defines the minimum and maximum values for each
-    /// of the three dimensions of the marker data.
-    // TODO: This should be a 3x2 grid of floats, or even better a custom type
-    pub data_limits: Option<Grid<f32>>,
+    /// The per-axis minimum and maximum values the file's 3D point data
+    /// was expected to fall within during reconstruction.
+    pub data_limits: Option<DataLimits>,
     /// A float that defines the acceleration factor used in the calculation of
     /// a new segment. For gait analysis, this value is typically 50mm/sec^2.
     pub acc_factor: Option<f32>,
@@ -37,19 +163,8 @@ pub struct Seg {
 
 impl PartialEq for Seg {
     fn eq(&self, other: &Self) -> bool {
-        let data_limits_eq = if let Some(data_limits) = &self.data_limits {
-            if let Some(other_data_limits) = &other.data_limits {
-                data_limits.flatten() == other_data_limits.flatten()
-            } else {
-                false
-            }
-        } else if other.data_limits.is_some() {
-            false
-        } else {
-            true
-        };
         self.marker_diameter == other.marker_diameter
-            && data_limits_eq
+            && self.data_limits == other.data_limits
             && self.acc_factor == other.acc_factor
             && self.noise_factor == other.noise_factor
             && self.residual_error_factor == other.residual_error_factor
@@ -64,7 +179,15 @@ impl ToString for Seg {
             string.push_str(&format!("Marker Diameter: {}\n", marker_diameter));
         }
         if let Some(data_limits) = &self.data_limits {
-            string.push_str(&format!("Data Limits: {:?}\n", data_limits));
+            string.push_str(&format!(
+                "Data Limits: X [{}, {}], Y [{}, {}], Z [{}, {}]\n",
+                data_limits.x_min(),
+                data_limits.x_max(),
+                data_limits.y_min(),
+                data_limits.y_max(),
+                data_limits.z_min(),
+                data_limits.z_max(),
+            ));
         }
         if let Some(acc_factor) = &self.acc_factor {
             string.push_str(&format!("Acc Factor: {}\n", acc_factor));
@@ -93,9 +216,12 @@ impl Seg {
             Some(parameter) => Some(parameter.as_ref().try_into()?),
         };
         let data_limits_parameter = parameters.remove("SEG", "DATA_LIMITS");
-        let data_limits: Option<Grid<f32>> = match data_limits_parameter {
+        let data_limits: Option<DataLimits> = match data_limits_parameter {
             None => None,
-            Some(parameter) => Some(parameter.as_ref().try_into()?),
+            Some(parameter) => {
+                let grid: Grid<f32> = parameter.as_ref().try_into()?;
+                Some(DataLimits::from_grid(&grid)?)
+            }
         };
         let acc_factor = parameters.remove("SEG", "ACC_FACTOR");
         let acc_factor: Option<f32> = match acc_factor {
@@ -142,8 +268,8 @@ impl Seg {
                 false,
             )?);
         }
-        if self.data_limits.is_some() {
-            bytes.extend(Parameter::float_grid(self.data_limits.clone().unwrap()).write(
+        if let Some(data_limits) = &self.data_limits {
+            bytes.extend(Parameter::float_grid(data_limits.into_grid()).write(
                 processor,
                 "DATA_LIMITS".to_string(),
                 group_names_to_ids["SEG"],
@@ -184,4 +310,48 @@ impl Seg {
         }
         Ok(bytes)
     }
+
+    /// Scans `points`, the file's 3D point trajectories, against
+    /// `data_limits`. If `data_limits` is not yet set, it is filled in with
+    /// the bounds observed in `points` and this call returns an empty list,
+    /// since bounds computed from the data can never flag that same data as
+    /// out of range. When `data_limits` was already set (as stored in the
+    /// file), the existing bounds are left untouched and this returns the
+    /// coordinates of any points that fall outside them, which the SEG
+    /// section was designed to help catch as likely reconstruction errors.
+    pub fn compute_data_limits(
+        &mut self,
+        points: &[[f32; 3]],
+    ) -> Result<Vec<[f32; 3]>, C3dParseError> {
+        if points.is_empty() {
+            return Ok(Vec::new());
+        }
+        let limits = match self.data_limits {
+            Some(limits) => limits,
+            None => {
+                let mut x_min = f32::INFINITY;
+                let mut x_max = f32::NEG_INFINITY;
+                let mut y_min = f32::INFINITY;
+                let mut y_max = f32::NEG_INFINITY;
+                let mut z_min = f32::INFINITY;
+                let mut z_max = f32::NEG_INFINITY;
+                for point in points {
+                    x_min = x_min.min(point[0]);
+                    x_max = x_max.max(point[0]);
+                    y_min = y_min.min(point[1]);
+                    y_max = y_max.max(point[1]);
+                    z_min = z_min.min(point[2]);
+                    z_max = z_max.max(point[2]);
+                }
+                let computed = DataLimits::new(x_min, x_max, y_min, y_max, z_min, z_max)?;
+                self.data_limits = Some(computed);
+                computed
+            }
+        };
+        Ok(points
+            .iter()
+            .filter(|point| !limits.contains(point))
+            .copied()
+            .collect())
+    }
 }